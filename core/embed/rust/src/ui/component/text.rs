@@ -1,7 +1,7 @@
 use core::iter::{Enumerate, Peekable};
 use core::slice;
 
-use heapless::LinearMap;
+use heapless::{LinearMap, Vec as LimitedVec};
 
 use crate::ui::{
     display,
@@ -61,22 +61,51 @@ impl<'arg> Text<'arg> {
         self
     }
 
+    pub fn with_wrap_indent(mut self, wrap_indent: i32) -> Self {
+        self.layout.wrap_indent = wrap_indent;
+        self
+    }
+
+    /// Enables the wrap-continuation marker at the end of overflow-driven
+    /// wrapped lines. Off by default, so existing callers keep their current
+    /// rendered output until they opt in.
+    pub fn with_wrap_marker(mut self, wrap_marker_enabled: bool) -> Self {
+        self.layout.wrap_marker_enabled = wrap_marker_enabled;
+        self
+    }
+
     pub fn layout_mut(&mut self) -> &mut TextLayout {
         &mut self.layout
     }
 
     fn layout_content(&self, sink: &mut dyn LayoutSink) {
-        self.layout.clone().layout_formatted(
-            self.format,
-            |arg| match arg {
-                Token::Literal(literal) => Some(Op::Text(literal)),
-                Token::Argument(b"mono") => Some(Op::Font(theme::FONT_MONO)),
-                Token::Argument(b"bold") => Some(Op::Font(theme::FONT_BOLD)),
-                Token::Argument(b"normal") => Some(Op::Font(theme::FONT_NORMAL)),
-                Token::Argument(argument) => self.args.get(argument).map(|value| Op::Text(value)),
-            },
-            sink,
-        );
+        self.layout
+            .clone()
+            .layout_formatted(self.format, |arg| self.resolve_token(arg), sink);
+    }
+
+    fn resolve_token(&self, token: Token<'static>) -> Option<Op<'arg>> {
+        match token {
+            Token::Literal(literal) => Some(Op::Text(literal)),
+            Token::Argument(b"mono") => Some(Op::Font(theme::FONT_MONO)),
+            Token::Argument(b"bold") => Some(Op::Font(theme::FONT_BOLD)),
+            Token::Argument(b"normal") => Some(Op::Font(theme::FONT_NORMAL)),
+            Token::Argument(argument) => self.args.get(argument).map(|value| Op::Text(value)),
+        }
+    }
+
+    /// Screen position of the character at byte `offset` of the formatted
+    /// content, if any -- see `TextLayout::resolve_offset`.
+    pub fn resolve_offset(&self, offset: usize) -> Option<Point> {
+        self.layout
+            .resolve_offset(self.format, |arg| self.resolve_token(arg), offset)
+    }
+
+    /// Byte offset, into the formatted content, of whichever character ends
+    /// up closest to `point` -- see `TextLayout::resolve_point`.
+    pub fn resolve_point(&self, point: Point) -> Option<usize> {
+        self.layout
+            .resolve_point(self.format, |arg| self.resolve_token(arg), point)
     }
 }
 
@@ -111,6 +140,10 @@ mod trace {
             self.0.str("...");
         }
 
+        fn wrap_marker(&mut self, _cursor: Point, _layout: &TextLayout) {
+            self.0.str(">");
+        }
+
         fn line_break(&mut self, _cursor: Point) {
             self.0.str("\n");
         }
@@ -142,6 +175,11 @@ pub enum LineBreaking {
     /// Break words, adding a hyphen before the line-break. Does not use any
     /// smart algorithm, just char-by-char.
     BreakWordsAndInsertHyphen,
+    /// Break the whole text token at once using a total-fit pass (in the
+    /// style of the Knuth-Plass algorithm) instead of committing to the
+    /// first feasible break, trading a bit of extra work for a less ragged
+    /// right edge on multi-line screens.
+    Balanced,
 }
 
 #[derive(Copy, Clone)]
@@ -179,6 +217,22 @@ pub struct TextLayout {
     pub ellipsis_font: Font,
     /// Foreground color used for drawing the ellipsis.
     pub ellipsis_color: Color,
+
+    /// Horizontal offset applied to the cursor at the start of a line
+    /// produced by an overflow-driven wrap (never by a mandatory LF/CR
+    /// break), so wrapped addresses and recovery phrases hang off the
+    /// margin instead of starting flush at `bounds.x0`. Zero by default,
+    /// which keeps the old flush-left behavior.
+    pub wrap_indent: i32,
+    /// Whether to draw the wrap-continuation marker at the end of an
+    /// overflow-driven wrapped line (skipped if a hyphen was already
+    /// inserted there). Off by default, since turning it on changes
+    /// rendered output.
+    pub wrap_marker_enabled: bool,
+    /// Font used for the wrap-continuation marker.
+    pub wrap_marker_font: Font,
+    /// Foreground color used for the wrap-continuation marker.
+    pub wrap_marker_color: Color,
 }
 
 impl TextLayout {
@@ -194,6 +248,10 @@ impl TextLayout {
             page_breaking: PageBreaking::CutAndInsertEllipsis,
             ellipsis_font: theme::FONT_BOLD,
             ellipsis_color: theme::GREY_LIGHT,
+            wrap_indent: 0,
+            wrap_marker_enabled: false,
+            wrap_marker_font: theme::FONT_BOLD,
+            wrap_marker_color: theme::GREY_LIGHT,
         }
     }
 
@@ -208,11 +266,13 @@ impl TextLayout {
         I: IntoIterator<Item = Op<'op>>,
     {
         let mut cursor = self.initial_cursor();
+        let mut width_cache = WidthCache::new();
 
         self.layout_op_stream(
             &mut Tokenizer::new(format).flat_map(resolve),
             &mut cursor,
             sink,
+            &mut width_cache,
         )
     }
 
@@ -228,6 +288,7 @@ impl TextLayout {
         ops: &mut dyn Iterator<Item = Op<'op>>,
         cursor: &mut Point,
         sink: &mut dyn LayoutSink,
+        width_cache: &mut WidthCache,
     ) -> LayoutFit {
         let mut total_processed_chars = 0;
 
@@ -238,8 +299,9 @@ impl TextLayout {
                 }
                 Op::Font(font) => {
                     self.text_font = font;
+                    width_cache.clear();
                 }
-                Op::Text(text) => match self.layout_text(text, cursor, sink) {
+                Op::Text(text) => match self.layout_text(text, cursor, sink, width_cache) {
                     LayoutFit::Fitting { processed_chars } => {
                         total_processed_chars += processed_chars;
                     }
@@ -264,20 +326,28 @@ impl TextLayout {
         text: &[u8],
         cursor: &mut Point,
         sink: &mut dyn LayoutSink,
+        width_cache: &mut WidthCache,
     ) -> LayoutFit {
+        if matches!(self.line_breaking, LineBreaking::Balanced) {
+            return self.layout_text_balanced(text, cursor, sink, width_cache);
+        }
+
         let mut remaining_text = text;
 
         while !remaining_text.is_empty() {
             let span = Span::fit_horizontally(
                 remaining_text,
                 self.bounds.x1 - cursor.x,
-                self.text_font,
-                self.hyphen_font,
+                self,
                 self.line_breaking,
+                width_cache,
             );
 
             // Report the span at the cursor position.
             sink.text(*cursor, &self, &remaining_text[..span.length]);
+            if span.skip_next_chars > 0 {
+                sink.skip(span.skip_next_chars);
+            }
 
             // Continue with the rest of the remaining_text.
             remaining_text = &remaining_text[span.length + span.skip_next_chars..];
@@ -315,8 +385,24 @@ impl TextLayout {
                         processed_chars: text.len() - remaining_text.len(),
                     };
                 } else {
-                    // Advance the cursor to the beginning of the next line.
+                    // The wrap marker, if any, belongs at the end of the
+                    // line we're wrapping away from -- only for an
+                    // overflow-driven wrap, and only if no hyphen already
+                    // marks the break.
+                    if self.wrap_marker_enabled
+                        && !span.mandatory_break
+                        && !span.insert_hyphen_before_line_break
+                    {
+                        sink.wrap_marker(*cursor, &self);
+                    }
+
+                    // A mandatory break resets flush to the margin; an
+                    // overflow-driven wrap hangs off it by `wrap_indent`
+                    // instead.
                     cursor.x = self.bounds.x0;
+                    if !span.mandatory_break {
+                        cursor.x += self.wrap_indent;
+                    }
                     cursor.y += span.advance.y;
 
                     // Report a line break. While rendering works using the cursor coordinates, we use explicit line-break reporting in the `ufmt::uDebug` impl.
@@ -329,6 +415,146 @@ impl TextLayout {
             processed_chars: text.len(),
         }
     }
+
+    /// Same contract as `layout_text`, but for `LineBreaking::Balanced`:
+    /// instead of calling `Span::fit_horizontally` one line at a time, it
+    /// asks `Span::compute_balanced_breaks` to solve the whole text token (or
+    /// as much of it as fits the bounded item list) in one pass, then replays
+    /// the resulting spans through the exact same sink calls as the greedy
+    /// loop above.
+    fn layout_text_balanced(
+        &self,
+        text: &[u8],
+        cursor: &mut Point,
+        sink: &mut dyn LayoutSink,
+        width_cache: &mut WidthCache,
+    ) -> LayoutFit {
+        let mut remaining_text = text;
+
+        while !remaining_text.is_empty() {
+            // `marker_width` is reserved off every line's target width, not
+            // only at candidate breakpoints as in `fit_horizontally`, so lines
+            // that end without a marker lose that room as well. The breaker
+            // scores a line by its distance from this target in either
+            // direction, and accepts a line that exceeds it by no more than
+            // the line's total glue shrink.
+            let marker_width = wrap_marker_width(self, width_cache);
+            let breaks = Span::compute_balanced_breaks(
+                remaining_text,
+                self.bounds.x1 - cursor.x - marker_width,
+                self.bounds.x1 - self.bounds.x0 - self.wrap_indent - marker_width,
+                self,
+                width_cache,
+            );
+
+            for span in breaks.iter() {
+                sink.text(*cursor, &self, &remaining_text[..span.length]);
+                if span.skip_next_chars > 0 {
+                    sink.skip(span.skip_next_chars);
+                }
+
+                remaining_text = &remaining_text[span.length + span.skip_next_chars..];
+
+                cursor.x += span.advance.x;
+
+                if span.advance.y > 0 {
+                    if span.insert_hyphen_before_line_break {
+                        sink.hyphen(*cursor, &self);
+                    }
+                    if cursor.y + span.advance.y > self.bounds.y1 {
+                        if !remaining_text.is_empty() {
+                            let should_append_ellipsis =
+                                matches!(self.page_breaking, PageBreaking::CutAndInsertEllipsis)
+                                    && !span.insert_hyphen_before_line_break;
+                            if should_append_ellipsis {
+                                sink.ellipsis(*cursor, &self);
+                            }
+                        }
+
+                        sink.out_of_bounds();
+
+                        return LayoutFit::OutOfBounds {
+                            processed_chars: text.len() - remaining_text.len(),
+                        };
+                    } else {
+                        if self.wrap_marker_enabled
+                            && !span.mandatory_break
+                            && !span.insert_hyphen_before_line_break
+                        {
+                            sink.wrap_marker(*cursor, &self);
+                        }
+
+                        cursor.x = self.bounds.x0;
+                        if !span.mandatory_break {
+                            cursor.x += self.wrap_indent;
+                        }
+                        cursor.y += span.advance.y;
+
+                        sink.line_break(*cursor);
+                    }
+                }
+            }
+        }
+
+        LayoutFit::Fitting {
+            processed_chars: text.len(),
+        }
+    }
+
+    /// Runs a fresh layout pass over `format`, resolved the same way
+    /// `layout_formatted` resolves it, and returns the screen position of
+    /// the character at byte `offset` of the formatted content, if any --
+    /// see `PositionRecorder`. Goes through `layout_op_stream` rather than a
+    /// bare `layout_text` so `Op::Font`/`Op::Color` and argument
+    /// substitution land on the same positions the real paint pass draws.
+    pub fn resolve_offset<'op, F, I>(
+        &self,
+        format: &'static str,
+        resolve: F,
+        offset: usize,
+    ) -> Option<Point>
+    where
+        F: Fn(Token<'static>) -> I,
+        I: IntoIterator<Item = Op<'op>>,
+    {
+        let mut recorder = PositionRecorder::new();
+        let mut cursor = self.initial_cursor();
+        let mut width_cache = WidthCache::new();
+        self.layout_op_stream(
+            &mut Tokenizer::new(format).flat_map(resolve),
+            &mut cursor,
+            &mut recorder,
+            &mut width_cache,
+        );
+        recorder.resolve_offset(offset)
+    }
+
+    /// Runs a fresh layout pass over `format`, resolved the same way
+    /// `layout_formatted` resolves it, and returns the byte offset, into the
+    /// formatted content, of whichever character ends up closest to `point`,
+    /// approximating a touch hit-test -- see `PositionRecorder`. Goes
+    /// through `layout_op_stream` for the same reason `resolve_offset` does.
+    pub fn resolve_point<'op, F, I>(
+        &self,
+        format: &'static str,
+        resolve: F,
+        point: Point,
+    ) -> Option<usize>
+    where
+        F: Fn(Token<'static>) -> I,
+        I: IntoIterator<Item = Op<'op>>,
+    {
+        let mut recorder = PositionRecorder::new();
+        let mut cursor = self.initial_cursor();
+        let mut width_cache = WidthCache::new();
+        self.layout_op_stream(
+            &mut Tokenizer::new(format).flat_map(resolve),
+            &mut cursor,
+            &mut recorder,
+            &mut width_cache,
+        );
+        recorder.resolve_point(point)
+    }
 }
 
 pub enum LayoutFit {
@@ -343,12 +569,128 @@ pub trait LayoutSink {
     fn ellipsis(&mut self, _cursor: Point, _layout: &TextLayout) {}
     fn line_break(&mut self, _cursor: Point) {}
     fn out_of_bounds(&mut self) {}
+    /// Called at the end of a wrapped line, right before the cursor moves to
+    /// the next one, when the wrap was overflow-driven (not a mandatory
+    /// LF/CR break) and no hyphen was already inserted there.
+    fn wrap_marker(&mut self, _cursor: Point, _layout: &TextLayout) {}
+    /// Reports that `bytes` bytes of source text were consumed between the
+    /// previous `text()` call and the next one without being rendered (the
+    /// whitespace discarded at a word-wrap point, or the character of a
+    /// mandatory LF/CR break). Sinks that reconstruct a
+    /// running byte offset across calls, such as `PositionRecorder`, need
+    /// this to stay in sync with the source text; everyone else can ignore
+    /// it.
+    fn skip(&mut self, _bytes: usize) {}
 }
 
 pub struct TextNoop;
 
 impl LayoutSink for TextNoop {}
 
+/// How many characters' positions a single `PositionRecorder` pass keeps.
+/// Screens that need hit-testing or highlighting only ever do so over a
+/// handful of lines at a time, so this stays small and bounded.
+const MAX_RECORDED_CHARS: usize = 256;
+
+#[derive(Copy, Clone)]
+struct CharPosition {
+    /// Byte offset of this character into the concatenation of all text
+    /// processed by this pass (i.e. the same counting `LayoutFit::
+    /// processed_chars` uses).
+    offset: usize,
+    /// Baseline position of this character's glyph.
+    point: Point,
+    /// Line height of the font this character was drawn with, so a query
+    /// point can be matched to the line whose vertical band it falls in.
+    line_height: i32,
+}
+
+/// A `LayoutSink` that doesn't draw anything, but instead records where
+/// every character of the laid-out text ended up on screen, so a caller can
+/// map a source byte offset to a screen position or vice versa -- for
+/// highlighting a sub-range of the text, or hit-testing a touch point.
+///
+/// Run a layout pass with this as the sink (instead of `TextRenderer`), then
+/// query the result with `resolve_offset`/`resolve_point`.
+pub struct PositionRecorder {
+    chars: LimitedVec<CharPosition, MAX_RECORDED_CHARS>,
+    next_offset: usize,
+}
+
+impl PositionRecorder {
+    pub fn new() -> Self {
+        Self {
+            chars: LimitedVec::new(),
+            next_offset: 0,
+        }
+    }
+
+    /// Returns the screen position of the character at byte `offset`, if it
+    /// was laid out and recorded.
+    pub fn resolve_offset(&self, offset: usize) -> Option<Point> {
+        self.chars
+            .iter()
+            .find(|recorded| recorded.offset == offset)
+            .map(|recorded| recorded.point)
+    }
+
+    /// Returns the byte offset of whichever recorded character is closest to
+    /// `point` on its line, approximating a touch hit-test. A character's
+    /// line is considered to contain `point` if `point.y` falls in the
+    /// vertical band `(baseline - line_height, baseline]`, rather than
+    /// requiring an exact baseline match, so a touch anywhere on the line --
+    /// not just pixel-perfect on the baseline -- resolves to it.
+    ///
+    /// A CR-broken line only advances the cursor by half of `line_height`
+    /// (see `Span::fit_horizontally`), so two adjacent lines' bands can
+    /// overlap; picking the closest baseline first, then searching by x only
+    /// among that baseline's characters, keeps such a line pair from both
+    /// claiming the same touch.
+    pub fn resolve_point(&self, point: Point) -> Option<usize> {
+        let baseline = self
+            .chars
+            .iter()
+            .filter(|recorded| {
+                point.y <= recorded.point.y && point.y > recorded.point.y - recorded.line_height
+            })
+            .min_by_key(|recorded| (recorded.point.y - point.y).abs())?
+            .point
+            .y;
+        self.chars
+            .iter()
+            .filter(|recorded| recorded.point.y == baseline)
+            .min_by_key(|recorded| (recorded.point.x - point.x).abs())
+            .map(|recorded| recorded.offset)
+    }
+}
+
+impl Default for PositionRecorder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LayoutSink for PositionRecorder {
+    fn text(&mut self, cursor: Point, layout: &TextLayout, text: &[u8]) {
+        let mut x = cursor.x;
+        for (_, ch, char_len) in CharBoundaries::new(text) {
+            let _ = self.chars.push(CharPosition {
+                offset: self.next_offset,
+                point: Point::new(x, cursor.y),
+                line_height: layout.text_font.line_height(),
+            });
+            // Each glyph here is only ever measured once, so there's no
+            // pass-scoped cache worth threading through just for this.
+            x += char_width(layout.text_font, ch);
+            self.next_offset += char_len;
+        }
+    }
+
+    fn skip(&mut self, bytes: usize) {
+        self.next_offset += bytes;
+    }
+}
+
 pub struct TextRenderer;
 
 impl LayoutSink for TextRenderer {
@@ -381,6 +723,16 @@ impl LayoutSink for TextRenderer {
             layout.background_color,
         );
     }
+
+    fn wrap_marker(&mut self, cursor: Point, layout: &TextLayout) {
+        display::text(
+            cursor,
+            b">",
+            layout.wrap_marker_font,
+            layout.wrap_marker_color,
+            layout.background_color,
+        );
+    }
 }
 
 #[derive(Copy, Clone, PartialEq, Eq)]
@@ -492,11 +844,172 @@ impl<'a> Op<'a> {
     }
 }
 
+/// A small subset of the UAX #14 line-break classes -- just enough to cover
+/// what the firmware fonts actually render. Everything not called out here
+/// (combining marks, complex scripts, ...) is treated as ordinary `Glue`.
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum LineBreakClass {
+    /// Mandatory break (LF, NEL, LS, PS). CR is handled next to this, rather
+    /// than folded into it, since it advances by only half a line height.
+    Mandatory,
+    /// A break is allowed right after this character: a space (which is also
+    /// discarded), or a hyphen already present in the text (which is kept).
+    BreakAfter,
+    /// CJK-style ideographs, each of which is its own breakable unit --
+    /// unlike Latin script, no word-joining whitespace is expected around
+    /// them.
+    Ideographic,
+    /// No break opportunity; part of the same unbreakable run as its
+    /// neighbours.
+    Glue,
+}
+
+fn classify_char(ch: char) -> LineBreakClass {
+    match ch {
+        '\n' | '\u{0085}' | '\u{2028}' | '\u{2029}' => LineBreakClass::Mandatory,
+        ' ' | '-' => LineBreakClass::BreakAfter,
+        // Non-breaking space: a space that must not be treated as a break
+        // opportunity.
+        '\u{00A0}' => LineBreakClass::Glue,
+        ch if is_ideographic(ch) => LineBreakClass::Ideographic,
+        _ => LineBreakClass::Glue,
+    }
+}
+
+/// Whether `ch` falls in one of the major CJK blocks, which UAX #14 gives
+/// "break-between" behaviour: unlike Latin text, a line may wrap between any
+/// two such characters without a joining space.
+fn is_ideographic(ch: char) -> bool {
+    matches!(ch as u32,
+        0x1100..=0x11FF   // Hangul Jamo
+        | 0x2E80..=0x303E // CJK radicals, Kangxi, CJK punctuation
+        | 0x3041..=0x33FF // Hiragana, Katakana, CJK compatibility
+        | 0x3400..=0x4DBF // CJK Unified Ideographs Extension A
+        | 0x4E00..=0x9FFF // CJK Unified Ideographs
+        | 0xAC00..=0xD7A3 // Hangul syllables
+        | 0xF900..=0xFAFF // CJK Compatibility Ideographs
+        | 0xFF00..=0xFFEF // Halfwidth and Fullwidth Forms
+        | 0x20000..=0x3FFFD // CJK Unified Ideographs Extension B and beyond
+    )
+}
+
+/// Measures one decoded character, rather than a raw byte, against `font`.
+fn char_width(font: Font, ch: char) -> i32 {
+    let mut buf = [0; 4];
+    font.text_width(ch.encode_utf8(&mut buf).as_bytes())
+}
+
+/// Advance width to reserve for the wrap-continuation marker so a line that
+/// ends in one can't fit it past `bounds.x1`. Zero when the marker is
+/// disabled, so it doesn't shrink the fitting width for callers who never
+/// opted in.
+fn wrap_marker_width(layout: &TextLayout, width_cache: &mut WidthCache) -> i32 {
+    if layout.wrap_marker_enabled {
+        width_cache.get_or_measure(layout.wrap_marker_font, '>')
+    } else {
+        0
+    }
+}
+
+/// How many distinct `(Font, char)` glyph widths a single layout pass keeps
+/// memoized. Device screens only ever mix a couple of fonts across at most a
+/// few dozen distinct glyphs per pass, so this stays small and bounded
+/// rather than growing with the text.
+const MAX_CACHED_WIDTHS: usize = 64;
+
+/// Memoizes `char_width` within a single layout pass. Threaded explicitly
+/// through `TextLayout::layout_text`/`layout_op_stream` and
+/// `Span::fit_horizontally`/`compute_balanced_breaks` rather than owned by
+/// `TextLayout` itself, so `TextLayout` stays a plain, `Copy` value -- the
+/// struct is `pub` and used across the firmware UI, and embedding an
+/// interior-mutable cache in it would have broken every call site relying on
+/// that.
+pub struct WidthCache {
+    widths: LinearMap<(Font, char), i32, MAX_CACHED_WIDTHS>,
+}
+
+impl WidthCache {
+    pub fn new() -> Self {
+        Self {
+            widths: LinearMap::new(),
+        }
+    }
+
+    fn get_or_measure(&mut self, font: Font, ch: char) -> i32 {
+        if let Some(width) = self.widths.get(&(font, ch)) {
+            return *width;
+        }
+        let width = char_width(font, ch);
+        // If the cache is full, just skip memoizing this one -- correctness
+        // doesn't depend on every glyph being cached.
+        let _ = self.widths.insert((font, ch), width);
+        width
+    }
+
+    fn clear(&mut self) {
+        self.widths.clear();
+    }
+}
+
+/// Iterates `text` as `(byte_offset, char, byte_len)` triples, always
+/// advancing by whole codepoints. Falls back to single-byte steps (decoding
+/// each offending byte as `U+FFFD`) on invalid UTF-8 so a malformed buffer
+/// degrades gracefully instead of panicking -- callers are expected to only
+/// ever slice `text` at the boundaries this yields, which keeps every
+/// subsequent call valid UTF-8 in practice.
+///
+/// This deliberately stops at codepoint boundaries, not extended grapheme
+/// cluster boundaries (UAX #29): the firmware's bitmap fonts have no glyphs
+/// for combining marks, so `Font::text_width`/rendering already treat a base
+/// character and a following combining mark as two independent glyphs, and
+/// grouping them here would just mean measuring and breaking around a
+/// cluster whose pieces are drawn without any visual relationship anyway.
+/// Scripts that rely on combining marks or other multi-codepoint clusters
+/// aren't supported by these fonts regardless of how this scanner iterates.
+struct CharBoundaries<'a> {
+    text: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> CharBoundaries<'a> {
+    fn new(text: &'a [u8]) -> Self {
+        Self { text, pos: 0 }
+    }
+}
+
+impl<'a> Iterator for CharBoundaries<'a> {
+    type Item = (usize, char, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let rest = self.text.get(self.pos..)?;
+        if rest.is_empty() {
+            return None;
+        }
+
+        let offset = self.pos;
+        let valid = match core::str::from_utf8(rest) {
+            Ok(s) => s,
+            Err(err) if err.valid_up_to() > 0 => {
+                core::str::from_utf8(&rest[..err.valid_up_to()]).unwrap()
+            }
+            Err(_) => {
+                self.pos += 1;
+                return Some((offset, char::REPLACEMENT_CHARACTER, 1));
+            }
+        };
+        let ch = valid.chars().next()?;
+        let len = ch.len_utf8();
+        self.pos += len;
+        Some((offset, ch, len))
+    }
+}
+
 struct Span {
-    /// How many characters from the input text this span is laying out.
+    /// How many bytes from the input text this span is laying out. Always a
+    /// codepoint boundary, never the middle of a multi-byte character.
     length: usize,
-    /// How many chars from the input text should we skip before fitting the
-    /// next span?
+    /// How many bytes from the input text should we skip before fitting the
+    /// next span? Also always a codepoint boundary.
     skip_next_chars: usize,
     /// By how much to offset the cursor after this span. If the vertical offset
     /// is bigger than zero, it means we are breaking the line.
@@ -504,26 +1017,29 @@ struct Span {
     /// If we are breaking the line, should we insert a hyphen right after this
     /// span to indicate a word-break?
     insert_hyphen_before_line_break: bool,
+    /// If we are breaking the line, is it because of a mandatory break (LF,
+    /// CR) rather than running out of horizontal space? Mandatory breaks
+    /// reset the next line flush to the margin and never draw a wrap marker;
+    /// overflow-driven wraps get `TextLayout::wrap_indent` and, unless a
+    /// hyphen was already inserted, the wrap marker.
+    mandatory_break: bool,
 }
 
 impl Span {
     fn fit_horizontally(
         text: &[u8],
         max_width: i32,
-        text_font: Font,
-        hyphen_font: Font,
+        layout: &TextLayout,
         breaking: LineBreaking,
+        width_cache: &mut WidthCache,
     ) -> Self {
-        const ASCII_LF: u8 = 10;
-        const ASCII_CR: u8 = 13;
-        const ASCII_SPACE: u8 = 32;
-        const ASCII_HYPHEN: u8 = 45;
-
-        fn is_whitespace(ch: u8) -> bool {
-            ch == ASCII_SPACE || ch == ASCII_LF || ch == ASCII_CR
-        }
-
-        let hyphen_width = hyphen_font.text_width(&[ASCII_HYPHEN]);
+        let text_font = layout.text_font;
+        let hyphen_width = width_cache.get_or_measure(layout.hyphen_font, '-');
+        // Reserved only for breakpoints that may end up drawing the wrap
+        // marker (BreakAfter, Ideographic) -- not for the overflow check
+        // itself, so a line that exactly fills `max_width` still packs
+        // tight instead of wrapping one character early.
+        let marker_width = wrap_marker_width(layout, width_cache);
 
         // The span we return in case the line has to break. We mutate it in the
         // possible break points, and its initial value is returned in case no text
@@ -534,48 +1050,82 @@ impl Span {
             advance: Offset::new(0, text_font.line_height()),
             insert_hyphen_before_line_break: false,
             skip_next_chars: 0,
+            mandatory_break: false,
         };
 
         let mut span_width = 0;
         let mut found_any_whitespace = false;
+        let mut prev_class = LineBreakClass::Glue;
 
-        for (i, &ch) in text.iter().enumerate() {
-            let char_width = text_font.text_width(&[ch]);
+        for (offset, ch, char_len) in CharBoundaries::new(text) {
+            if ch == '\r' {
+                // Carriage return: break immediately, but advance the cursor
+                // only by half of the regular line height.
+                line.length = offset;
+                line.advance.x = span_width;
+                line.insert_hyphen_before_line_break = false;
+                line.skip_next_chars = char_len;
+                line.advance.y = text_font.line_height() / 2;
+                line.mandatory_break = true;
+                return line;
+            }
 
-            // Consider if we could be breaking the line at this position.
-            if is_whitespace(ch) {
-                // Break before the whitespace, without hyphen.
-                line.length = i;
+            let class = classify_char(ch);
+            let width = width_cache.get_or_measure(text_font, ch);
+
+            if class == LineBreakClass::Mandatory {
+                // End of line, break immediately, without hyphen.
+                line.length = offset;
                 line.advance.x = span_width;
                 line.insert_hyphen_before_line_break = false;
-                line.skip_next_chars = 1;
-                if ch == ASCII_CR {
-                    // We'll be breaking the line, but advancing the cursor only by a half of the
-                    // regular line height.
-                    line.advance.y = text_font.line_height() / 2;
-                }
-                if ch == ASCII_LF || ch == ASCII_CR {
-                    // End of line, break immediately.
-                    return line;
-                }
-                found_any_whitespace = true;
-            } else if span_width + char_width > max_width {
+                line.skip_next_chars = char_len;
+                line.mandatory_break = true;
+                return line;
+            }
+
+            // Ideographs break on either side, so a break right before this
+            // character is also a candidate, independent of its neighbour.
+            if (class == LineBreakClass::Ideographic || prev_class == LineBreakClass::Ideographic)
+                && span_width + marker_width <= max_width
+            {
+                line.length = offset;
+                line.advance.x = span_width;
+                line.insert_hyphen_before_line_break = false;
+                line.skip_next_chars = 0;
+            }
+
+            if span_width + width > max_width {
                 // Return the last breakpoint.
                 return line;
-            } else {
-                let have_space_for_break = span_width + char_width + hyphen_width <= max_width;
+            }
+
+            if class == LineBreakClass::BreakAfter {
+                // Break after this character. A space is discarded; an
+                // existing hyphen is kept as part of the line. Only record
+                // it as the candidate if there's still room for the wrap
+                // marker, so we don't later draw one past `max_width`.
+                found_any_whitespace |= ch == ' ';
+                if span_width + width + marker_width <= max_width {
+                    line.length = offset + char_len;
+                    line.advance.x = span_width + width;
+                    line.insert_hyphen_before_line_break = false;
+                    line.skip_next_chars = if ch == ' ' { char_len } else { 0 };
+                }
+            } else if class == LineBreakClass::Glue {
+                let have_space_for_break = span_width + width + hyphen_width <= max_width;
                 let can_break_word = matches!(breaking, LineBreaking::BreakWordsAndInsertHyphen)
                     || !found_any_whitespace;
                 if have_space_for_break && can_break_word {
                     // Break after this character, append hyphen.
-                    line.length = i + 1;
-                    line.advance.x = span_width + char_width;
+                    line.length = offset + char_len;
+                    line.advance.x = span_width + width;
                     line.insert_hyphen_before_line_break = true;
                     line.skip_next_chars = 0;
                 }
             }
 
-            span_width += char_width;
+            span_width += width;
+            prev_class = class;
         }
 
         // The whole text is fitting.
@@ -584,10 +1134,476 @@ impl Span {
             advance: Offset::new(span_width, 0),
             insert_hyphen_before_line_break: false,
             skip_next_chars: 0,
+            mandatory_break: false,
         }
     }
 }
 
+/// Items considered in a single `Span::compute_balanced_breaks` pass, and
+/// active breakpoints kept alive while solving it. Both are bounded so the
+/// optimal breaker stays heapless/no_std friendly -- a text token longer than
+/// this falls back to running the pass again on the remainder, which is
+/// still correct but loses the whole-token view the balanced breaker relies
+/// on to pick where to split: the item list gets cut off mid-token and the
+/// remainder restarts as if it were a fresh line, rather than being
+/// considered together with what came before. Each non-space glyph emits two
+/// items (a `Box` plus a breakpoint `Penalty`), and inter-word spaces emit
+/// one `Glue` each, so this bound needs to be sized in *items*, not
+/// characters: 128 items covers a worst-case line of roughly 64 consecutive
+/// non-space characters -- a full-width address or recovery-word line on any
+/// of this firmware's screens -- with headroom left over.
+const MAX_BALANCED_ITEMS: usize = 128;
+const MAX_BALANCED_ACTIVE: usize = 128;
+
+/// One element of the Knuth-Plass item stream built from a text token.
+#[derive(Copy, Clone)]
+enum BreakItem {
+    /// An unbreakable glyph, measured by `Font::text_width`.
+    Box { width: i32 },
+    /// Inter-word whitespace: a break opportunity with a natural width and a
+    /// stretch/shrink allowance.
+    Glue { width: i32, stretch: i32, shrink: i32 },
+    /// An optional break taken only if it improves the overall fit; `hyphen`
+    /// tells the caller whether taking it should draw a synthetic hyphen
+    /// (inserted mid-word) or not (an already-present hyphen, or a break
+    /// between ideographs).
+    Penalty {
+        width: i32,
+        penalty: i32,
+        hyphen: bool,
+    },
+    /// A mandatory break (line-feed or carriage-return). Always taken, and
+    /// never penalized for raggedness.
+    Forced { advance_y: i32 },
+}
+
+/// A single entry of the item stream, together with the byte range of the
+/// source text it covers.
+#[derive(Copy, Clone)]
+struct BreakItemEntry {
+    kind: BreakItem,
+    /// Byte offset, exclusive, where a line ending at this item stops (what
+    /// `Span::length` should be measured against if this break is taken).
+    content_end: usize,
+    /// Bytes to skip before the next line starts, mirroring
+    /// `Span::skip_next_chars` (the byte length of a consumed space or
+    /// mandatory-break character, 0 otherwise).
+    skip: usize,
+}
+
+/// A candidate line ending kept alive while solving the DP.
+#[derive(Copy, Clone)]
+struct ActiveNode {
+    /// Cumulative natural width/stretch/shrink of all items up to (but not
+    /// including) this breakpoint, so a line's width is a plain subtraction.
+    width: i32,
+    stretch: i32,
+    shrink: i32,
+    /// 0 for the first line of the pass, 1+ afterwards -- distinguishes the
+    /// (possibly shorter) first line from the rest.
+    line_index: usize,
+    /// Total demerits of the best known sequence of lines ending here.
+    demerits: u32,
+    /// Index, into the `nodes` list, of this node's predecessor.
+    predecessor: Option<usize>,
+    /// Index, into the item stream, of the break this node was created at.
+    /// `None` for the sentinel start-of-pass node.
+    at_item: Option<usize>,
+}
+
+impl Span {
+    /// Solves for the minimum-raggedness sequence of breaks across as much
+    /// of `text` as fits the bounded item list, starting a pass that ends
+    /// either at the first forced break (LF/CR) or, if none is found, once
+    /// the whole prefix has been measured. Returns the chosen line spans in
+    /// order; the caller re-invokes this for whatever text remains.
+    fn compute_balanced_breaks(
+        text: &[u8],
+        first_line_width: i32,
+        full_line_width: i32,
+        layout: &TextLayout,
+        width_cache: &mut WidthCache,
+    ) -> LimitedVec<Span, MAX_BALANCED_ITEMS> {
+        const MID_WORD_BREAK_PENALTY: i32 = 800;
+
+        let text_font = layout.text_font;
+        let hyphen_width = width_cache.get_or_measure(layout.hyphen_font, '-');
+        let space_width = width_cache.get_or_measure(text_font, ' ');
+
+        // Build the item stream for as much of `text` as the bounded list
+        // allows, stopping early at the first mandatory break. `consumed`
+        // tracks how many bytes of `text` ended up tokenized: if it's less
+        // than `text.len()`, the item list was truncated by `MAX_BALANCED_
+        // ITEMS` rather than by reaching the end of `text`, which matters
+        // for how the last line of this pass is reconstructed below.
+        let mut items: LimitedVec<BreakItemEntry, MAX_BALANCED_ITEMS> = LimitedVec::new();
+        let mut consumed = text.len();
+        let mut prev_class = LineBreakClass::Glue;
+
+        for (offset, ch, char_len) in CharBoundaries::new(text) {
+            if items.is_full() {
+                consumed = offset;
+                break;
+            }
+
+            if ch == '\r' {
+                let _ = items.push(BreakItemEntry {
+                    kind: BreakItem::Forced {
+                        advance_y: text_font.line_height() / 2,
+                    },
+                    content_end: offset,
+                    skip: char_len,
+                });
+                consumed = offset + char_len;
+                break;
+            }
+
+            let class = classify_char(ch);
+            match class {
+                LineBreakClass::Mandatory => {
+                    let _ = items.push(BreakItemEntry {
+                        kind: BreakItem::Forced {
+                            advance_y: text_font.line_height(),
+                        },
+                        content_end: offset,
+                        skip: char_len,
+                    });
+                    consumed = offset + char_len;
+                    break;
+                }
+                LineBreakClass::BreakAfter if ch == ' ' => {
+                    let _ = items.push(BreakItemEntry {
+                        kind: BreakItem::Glue {
+                            width: space_width,
+                            stretch: space_width / 2,
+                            shrink: space_width / 3,
+                        },
+                        content_end: offset,
+                        skip: char_len,
+                    });
+                }
+                _ => {
+                    // An ordinary glyph, an existing hyphen (kept as part of
+                    // the line), or an ideograph: all contribute their own
+                    // width as an unbreakable box, optionally followed by a
+                    // break opportunity right after it.
+                    let width = width_cache.get_or_measure(text_font, ch);
+                    let _ = items.push(BreakItemEntry {
+                        kind: BreakItem::Box { width },
+                        content_end: offset + char_len,
+                        skip: 0,
+                    });
+                    if items.is_full() {
+                        consumed = offset + char_len;
+                        continue;
+                    }
+                    let breakpoint = match class {
+                        LineBreakClass::BreakAfter => Some((0, 0, false)),
+                        LineBreakClass::Ideographic => Some((0, 0, false)),
+                        LineBreakClass::Glue if prev_class == LineBreakClass::Ideographic => {
+                            Some((0, 0, false))
+                        }
+                        LineBreakClass::Glue => {
+                            Some((MID_WORD_BREAK_PENALTY, hyphen_width, true))
+                        }
+                        LineBreakClass::Mandatory => unreachable!(),
+                    };
+                    if let Some((penalty, width, hyphen)) = breakpoint {
+                        let _ = items.push(BreakItemEntry {
+                            kind: BreakItem::Penalty {
+                                width,
+                                penalty,
+                                hyphen,
+                            },
+                            content_end: offset + char_len,
+                            skip: 0,
+                        });
+                    }
+                }
+            }
+            prev_class = class;
+        }
+
+        if items.is_empty() {
+            return LimitedVec::new();
+        }
+        let truncated = consumed < text.len();
+
+        // Prefix sums of width/stretch/shrink, measured before each item.
+        let mut prefix_width = [0i32; MAX_BALANCED_ITEMS + 1];
+        let mut prefix_stretch = [0i32; MAX_BALANCED_ITEMS + 1];
+        let mut prefix_shrink = [0i32; MAX_BALANCED_ITEMS + 1];
+        for (k, entry) in items.iter().enumerate() {
+            let (w, s, sh) = match entry.kind {
+                BreakItem::Box { width } => (width, 0, 0),
+                BreakItem::Glue {
+                    width,
+                    stretch,
+                    shrink,
+                } => (width, stretch, shrink),
+                BreakItem::Penalty { .. } | BreakItem::Forced { .. } => (0, 0, 0),
+            };
+            prefix_width[k + 1] = prefix_width[k] + w;
+            prefix_stretch[k + 1] = prefix_stretch[k] + s;
+            prefix_shrink[k + 1] = prefix_shrink[k] + sh;
+        }
+
+        // The first line of a pass (line_index 0) targets `first_line_width`
+        // (the caller leaves room for what's already on the cursor's line);
+        // every line after it targets the full `full_line_width`.
+        fn line_target(line_index: usize, first_line_width: i32, full_line_width: i32) -> i32 {
+            if line_index == 0 {
+                first_line_width
+            } else {
+                full_line_width
+            }
+        }
+
+        // A line is feasible if it doesn't overflow past its shrink budget --
+        // Knuth-Plass's adjustment ratio `r < -1`. Candidates that fail this
+        // are still scored (see below), but only as a last-resort fallback:
+        // it's what makes a balanced multi-line split ever win over a single
+        // overfull line, since an overfull line must lose outright rather
+        // than merely score worse.
+        fn line_feasible(content_width: i32, shrink: i32, target: i32) -> bool {
+            (content_width as i64 - target as i64) <= shrink as i64
+        }
+
+        // Demerits for a line of the given content width/stretch/shrink
+        // against its target width: `100 * |r|^3` badness, `(1 + badness +
+        // penalty)^2` demerits, where `r` is the adjustment ratio.
+        // Integer reimplementation of the Knuth-Plass adjustment-ratio
+        // badness: this runs in the hot layout path on FPU-less targets, so
+        // it stays in i64 fixed-point rather than pulling in soft-float.
+        // `badness = 100 * |diff/denom|^3` is computed as `100 * |diff|^3 /
+        // denom^3`, which is exact integer division of the same quantity
+        // (no precision loss beyond the final truncation f32 would also
+        // incur).
+        fn line_demerits(content_width: i32, stretch: i32, shrink: i32, target: i32, penalty: i32) -> u32 {
+            let diff = (content_width - target) as i64;
+            let denom = if diff > 0 {
+                if shrink > 0 {
+                    shrink as i64
+                } else {
+                    1
+                }
+            } else if stretch > 0 {
+                stretch as i64
+            } else {
+                1
+            };
+            let numer_cubed = diff.unsigned_abs().saturating_pow(3) as i64;
+            let denom_cubed = denom.saturating_pow(3);
+            let badness = 100i64.saturating_mul(numer_cubed) / denom_cubed;
+            let d = 1i64 + badness + penalty as i64;
+            d.saturating_mul(d).min(u32::MAX as i64) as u32
+        }
+
+        let mut nodes: LimitedVec<ActiveNode, { MAX_BALANCED_ITEMS + 1 }> = LimitedVec::new();
+        let _ = nodes.push(ActiveNode {
+            width: 0,
+            stretch: 0,
+            shrink: 0,
+            line_index: 0,
+            demerits: 0,
+            predecessor: None,
+            at_item: None,
+        });
+        let mut active: LimitedVec<usize, MAX_BALANCED_ACTIVE> = LimitedVec::new();
+        let _ = active.push(0);
+
+        for k in 0..items.len() {
+            let forced = matches!(items[k].kind, BreakItem::Forced { .. });
+            let is_breakpoint = forced
+                || matches!(
+                    items[k].kind,
+                    BreakItem::Glue { .. } | BreakItem::Penalty { .. }
+                );
+            if !is_breakpoint {
+                continue;
+            }
+
+            let extra_width = match items[k].kind {
+                BreakItem::Penalty { width, .. } => width,
+                _ => 0,
+            };
+            let penalty = match items[k].kind {
+                BreakItem::Penalty { penalty, .. } => penalty,
+                _ => 0,
+            };
+
+            // `best_feasible` only considers candidates whose line doesn't
+            // overflow past its shrink budget; `best_any` tracks the best
+            // candidate regardless, as a fallback for the case where every
+            // active node would be overfull here (e.g. a single word wider
+            // than the line) -- without a fallback the active list could
+            // empty out and the breaker would stop making progress.
+            let mut best_feasible: Option<(usize, u32)> = None;
+            let mut best_any: Option<(usize, u32)> = None;
+            for &node_idx in active.iter() {
+                let node = nodes[node_idx];
+                let target = line_target(node.line_index, first_line_width, full_line_width);
+                let content_width = prefix_width[k] - node.width + extra_width;
+                let stretch = prefix_stretch[k] - node.stretch;
+                let shrink = prefix_shrink[k] - node.shrink;
+
+                let demerits_here = if forced {
+                    0
+                } else {
+                    line_demerits(content_width, stretch, shrink, target, penalty)
+                };
+                let total = node.demerits.saturating_add(demerits_here);
+                if best_any.map_or(true, |(_, d)| total < d) {
+                    best_any = Some((node_idx, total));
+                }
+                if (forced || line_feasible(content_width, shrink, target))
+                    && best_feasible.map_or(true, |(_, d)| total < d)
+                {
+                    best_feasible = Some((node_idx, total));
+                }
+            }
+
+            let Some((pred, demerits)) = best_feasible.or(best_any) else {
+                continue;
+            };
+            if nodes.is_full() {
+                continue;
+            }
+            let pred_node = nodes[pred];
+            let new_index = nodes.len();
+            let _ = nodes.push(ActiveNode {
+                width: prefix_width[k + 1],
+                stretch: prefix_stretch[k + 1],
+                shrink: prefix_shrink[k + 1],
+                line_index: pred_node.line_index + 1,
+                demerits,
+                predecessor: Some(pred),
+                at_item: Some(k),
+            });
+
+            if forced {
+                // A mandatory break discards every other candidate: there is
+                // only one way through a forced break.
+                active.clear();
+                let _ = active.push(new_index);
+                break;
+            } else if active.push(new_index).is_err() {
+                // Active list is at capacity; keep the nodes we already have
+                // rather than growing further. The DP stays correct, just
+                // with a narrower search.
+            }
+        }
+
+        // Implicit end-of-pass breakpoint: unless we stopped at a forced
+        // break (which already flushed `active` down to a single node), pick
+        // among the surviving candidates by costing each one's own trailing
+        // remainder -- from that node to the end of the measured prefix --
+        // as a real line, the same way every earlier line was costed. This
+        // applies whether or not the pass was truncated: the trailing
+        // remainder's cost is only ever used to choose between candidates
+        // here, never emitted as a break itself (a truncated pass still
+        // defers its actual unconsumed text to the next call). Comparing on
+        // accumulated demerits alone, without this, would always prefer the
+        // zero-break start node -- it hasn't paid for a single line yet --
+        // over any real multi-line split, since every real break adds at
+        // least 1 demerit.
+        let last_forced = matches!(items[items.len() - 1].kind, BreakItem::Forced { .. });
+        let end_index = if last_forced {
+            active[0]
+        } else {
+            let mut best_feasible: Option<(usize, u32)> = None;
+            let mut best_any: Option<(usize, u32)> = None;
+            for &node_idx in active.iter() {
+                let node = nodes[node_idx];
+                let target = line_target(node.line_index, first_line_width, full_line_width);
+                let content_width = prefix_width[items.len()] - node.width;
+                let stretch = prefix_stretch[items.len()] - node.stretch;
+                let shrink = prefix_shrink[items.len()] - node.shrink;
+                let trailing_demerits = line_demerits(content_width, stretch, shrink, target, 0);
+                let total = node.demerits.saturating_add(trailing_demerits);
+                if best_any.map_or(true, |(_, d)| total < d) {
+                    best_any = Some((node_idx, total));
+                }
+                if line_feasible(content_width, shrink, target)
+                    && best_feasible.map_or(true, |(_, d)| total < d)
+                {
+                    best_feasible = Some((node_idx, total));
+                }
+            }
+            best_feasible
+                .or(best_any)
+                .map(|(idx, _)| idx)
+                .unwrap_or(0)
+        };
+
+        // Reconstruct the chosen breaks by walking predecessor pointers back
+        // to the start, then reversing.
+        let mut chosen_items: LimitedVec<usize, MAX_BALANCED_ITEMS> = LimitedVec::new();
+        let mut cursor = end_index;
+        while let Some(at_item) = nodes[cursor].at_item {
+            let _ = chosen_items.push(at_item);
+            cursor = nodes[cursor].predecessor.unwrap();
+        }
+
+        // `line_start_item` is the index, into the item stream, of the first
+        // item belonging to the line currently being measured; `line_start`
+        // is the same position expressed as a byte offset into `text`. Both
+        // advance together as each chosen break is consumed.
+        let mut breaks: LimitedVec<Span, MAX_BALANCED_ITEMS> = LimitedVec::new();
+        let mut line_start_item = 0;
+        let mut line_start = 0;
+        for &item_index in chosen_items.iter().rev() {
+            let entry = items[item_index];
+            let advance_y = match entry.kind {
+                BreakItem::Forced { advance_y } => advance_y,
+                _ => text_font.line_height(),
+            };
+            let _ = breaks.push(Span {
+                length: entry.content_end - line_start,
+                skip_next_chars: entry.skip,
+                advance: Offset::new(prefix_width[item_index] - prefix_width[line_start_item], advance_y),
+                insert_hyphen_before_line_break: matches!(
+                    entry.kind,
+                    BreakItem::Penalty { hyphen: true, .. }
+                ),
+                mandatory_break: matches!(entry.kind, BreakItem::Forced { .. }),
+            });
+            line_start_item = item_index + 1;
+            line_start = entry.content_end + entry.skip;
+        }
+
+        if breaks.is_empty() {
+            // No feasible break was found anywhere in the bounded prefix.
+            // Either it's one unbreakable run that happens to fit (render it
+            // flush, no line break), or it doesn't and the bound was hit
+            // first -- force a wrap anyway so the caller always makes
+            // progress instead of spinning on the same text forever.
+            let _ = breaks.push(Span {
+                length: consumed,
+                skip_next_chars: 0,
+                advance: Offset::new(
+                    prefix_width[items.len()],
+                    if truncated { text_font.line_height() } else { 0 },
+                ),
+                insert_hyphen_before_line_break: false,
+                mandatory_break: false,
+            });
+        } else if !truncated && !last_forced && line_start < consumed {
+            // Whatever is left after the last chosen break, up to the end of
+            // this pass, forms the final, unbroken line.
+            let _ = breaks.push(Span {
+                length: consumed - line_start,
+                skip_next_chars: 0,
+                advance: Offset::new(prefix_width[items.len()] - prefix_width[line_start_item], 0),
+                insert_hyphen_before_line_break: false,
+                mandatory_break: false,
+            });
+        }
+
+        breaks
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -618,4 +1634,71 @@ mod tests {
             Token::Literal(b"}}}"),
         ])));
     }
+
+    // `Span::fit_horizontally`/`Span::compute_balanced_breaks` take a
+    // `&TextLayout`, which in turn needs a real `Font` to measure glyph
+    // widths -- `Font` is generated from the firmware's C bitmap font
+    // tables and isn't available to a plain `cargo test` run, so there's no
+    // way to assert an exact expected layout (line breaks, positions) from
+    // here. The tests below instead cover the parts of chunk0-1/chunk0-2
+    // that don't depend on glyph metrics: codepoint/CJK boundary scanning
+    // and classification.
+
+    #[test]
+    fn char_boundaries_walks_multibyte_codepoints() {
+        // "é" (U+00E9, 2 bytes), then "日" (U+65E5, 3 bytes), then "a".
+        let text = "é日a";
+        let chars: std::vec::Vec<_> = CharBoundaries::new(text.as_bytes()).collect();
+        assert_eq!(
+            chars,
+            std::vec![(0, 'é', 2), (2, '日', 3), (5, 'a', 1)],
+        );
+    }
+
+    #[test]
+    fn char_boundaries_recovers_from_invalid_utf8() {
+        // A lone continuation byte (0x80) is invalid on its own; it should
+        // decode as U+FFFD and advance by exactly one byte so the scan can
+        // resync on the valid ASCII that follows.
+        let text = [b'a', 0x80, b'b'];
+        let chars: std::vec::Vec<_> = CharBoundaries::new(&text).collect();
+        assert_eq!(
+            chars,
+            std::vec![
+                (0, 'a', 1),
+                (1, char::REPLACEMENT_CHARACTER, 1),
+                (2, 'b', 1),
+            ],
+        );
+    }
+
+    #[test]
+    fn char_boundaries_never_yields_a_mid_codepoint_offset() {
+        let text = "a日é本b";
+        let mut expected_next_offset = 0;
+        for (offset, _ch, len) in CharBoundaries::new(text.as_bytes()) {
+            assert_eq!(offset, expected_next_offset);
+            expected_next_offset = offset + len;
+        }
+        assert_eq!(expected_next_offset, text.len());
+    }
+
+    #[test]
+    fn classify_char_treats_cjk_as_ideographic() {
+        // Hiragana, a CJK Unified Ideograph, and a Hangul syllable all wrap
+        // on either side without needing a joining space.
+        for ch in ['あ', '日', '한'] {
+            assert!(classify_char(ch) == LineBreakClass::Ideographic);
+        }
+    }
+
+    #[test]
+    fn classify_char_keeps_latin_and_whitespace_distinct_from_ideographic() {
+        assert!(classify_char('a') == LineBreakClass::Glue);
+        assert!(classify_char(' ') == LineBreakClass::BreakAfter);
+        assert!(classify_char('-') == LineBreakClass::BreakAfter);
+        assert!(classify_char('\n') == LineBreakClass::Mandatory);
+        // Non-breaking space must never be treated as a break opportunity.
+        assert!(classify_char('\u{00A0}') == LineBreakClass::Glue);
+    }
 }